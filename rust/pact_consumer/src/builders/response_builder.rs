@@ -3,11 +3,11 @@ use std::collections::HashMap;
 use maplit::*;
 
 use pact_matching::models::*;
-use pact_matching::models::matchingrules::MatchingRules;
+use pact_matching::models::matchingrules::{HttpStatus, MatchingRule, MatchingRules, RuleLogic};
 use pact_models::bodies::OptionalBody;
 
 use crate::prelude::*;
-use pact_matching::models::generators::Generators;
+use pact_matching::models::generators::{Generator, GeneratorCategory, Generators};
 
 /// Builder for `Response` objects. Normally created via `PactBuilder`.
 pub struct ResponseBuilder {
@@ -62,6 +62,38 @@ impl ResponseBuilder {
         self.status(404)
     }
 
+    /// Match any response status code in the given class (e.g. any `2xx` success code),
+    /// instead of requiring the exact value set via `status`.
+    ///
+    /// ```
+    /// use pact_matching::models::matchingrules::HttpStatus;
+    /// use pact_consumer::builders::ResponseBuilder;
+    ///
+    /// let mut response = ResponseBuilder::default();
+    /// response.status_matching(HttpStatus::Success);
+    /// ```
+    pub fn status_matching(&mut self, status_class: HttpStatus) -> &mut Self {
+        self.response.matching_rules
+            .add_category("status")
+            .add_rule("", MatchingRule::StatusCode(status_class), RuleLogic::And);
+        self
+    }
+
+    /// Match the response status against an enumerated set of acceptable status codes,
+    /// instead of requiring the exact value set via `status`.
+    pub fn status_matching_codes<I>(&mut self, codes: I) -> &mut Self
+        where I: IntoIterator<Item = u16>
+    {
+        self.status_matching(HttpStatus::StatusCodes(codes.into_iter().collect()))
+    }
+
+    /// Set a generator that produces the mock server's response status dynamically,
+    /// instead of using the fixed value set via `status`.
+    pub fn status_generator(&mut self, generator: Generator) -> &mut Self {
+        self.response.generators.add_generator(&GeneratorCategory::STATUS, generator);
+        self
+    }
+
     /// Build the specified `Response` object.
     pub fn build(&self) -> Response {
         self.response.clone()
@@ -93,3 +125,37 @@ impl HttpPartBuilder for ResponseBuilder {
     )
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn status_matching_adds_a_status_code_matching_rule() {
+    let mut builder = ResponseBuilder::default();
+    builder.status_matching(HttpStatus::Success);
+    let response = builder.build();
+
+    let category = response.matching_rules.rules_for_category("status");
+    assert!(!category.is_empty());
+  }
+
+  #[test]
+  fn status_matching_codes_matches_an_enumerated_set_of_codes() {
+    let mut builder = ResponseBuilder::default();
+    builder.status_matching_codes(vec![200, 201, 204]);
+    let response = builder.build();
+
+    let category = response.matching_rules.rules_for_category("status");
+    assert!(!category.is_empty());
+  }
+
+  #[test]
+  fn status_generator_adds_a_status_generator() {
+    let mut builder = ResponseBuilder::default();
+    builder.status_generator(Generator::RandomInt(200, 299));
+    let response = builder.build();
+
+    assert!(!response.generators.is_empty());
+  }
+}
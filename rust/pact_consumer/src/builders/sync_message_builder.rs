@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+
+use maplit::hashmap;
+use serde_json::Value;
+
+use pact_matching::models::generators::Generators;
+use pact_matching::models::matchingrules::MatchingRules;
+use pact_matching::models::v4::message_parts::{ContentMatcher, MessageContents};
+use pact_matching::models::v4::sync_message::SynchronousMessages;
+use pact_models::bodies::OptionalBody;
+use pact_models::provider_states::ProviderState;
+
+/// Builder for `SynchronousMessages` interactions, i.e. a request message that is
+/// responded to with a sequence of one or more response messages. Created via
+/// `SyncMessageInteractionBuilder::new`.
+pub struct SyncMessageInteractionBuilder {
+    messages: SynchronousMessages,
+}
+
+impl SyncMessageInteractionBuilder {
+    /// Create a new interaction with the given description.
+    pub fn new<D: Into<String>>(description: D) -> Self {
+        SyncMessageInteractionBuilder {
+            messages: SynchronousMessages {
+                description: description.into(),
+                ..SynchronousMessages::default()
+            }
+        }
+    }
+
+    /// Specify a provider state for this interaction. Calling this multiple times
+    /// will add multiple provider states.
+    pub fn given<S: Into<String>>(&mut self, provider_state: S) -> &mut Self {
+        self.messages.provider_states.push(ProviderState {
+            name: provider_state.into(),
+            params: hashmap!{}
+        });
+        self
+    }
+
+    /// Add an annotation/comment to this interaction.
+    pub fn comment<S: Into<String>>(&mut self, name: S, value: Value) -> &mut Self {
+        self.messages.comments.insert(name.into(), value);
+        self
+    }
+
+    /// Mark this interaction as pending. Pending interactions will never fail the build
+    /// if they fail to match.
+    pub fn pending(&mut self, pending: bool) -> &mut Self {
+        self.messages.pending = pending;
+        self
+    }
+
+    /// Set the contents of the request message.
+    pub fn request_body<B: Into<OptionalBody>>(&mut self, body: B) -> &mut Self {
+        self.messages.request.contents = body.into();
+        self
+    }
+
+    /// Set a metadata value on the request message.
+    pub fn request_metadata<S: Into<String>>(&mut self, key: S, value: Value) -> &mut Self {
+        self.messages.request.metadata.insert(key.into(), value);
+        self
+    }
+
+    /// Get mutable access to the request's matching rules, so that matching rules can be
+    /// added for the request body or metadata.
+    pub fn request_matching_rules(&mut self) -> &mut MatchingRules {
+        &mut self.messages.request.matching_rules
+    }
+
+    /// Get mutable access to the request's generators, so that generators can be added
+    /// for the request body or metadata.
+    pub fn request_generators(&mut self) -> &mut Generators {
+        &mut self.messages.request.generators
+    }
+
+    /// Set the content matcher for the request message, e.g. `ContentMatcher::JsonRpc2` to
+    /// treat the request body as a JSON-RPC 2.0 envelope rather than matching it
+    /// byte-for-byte.
+    pub fn request_content_matcher(&mut self, content_matcher: ContentMatcher) -> &mut Self {
+        self.messages.request.content_matcher = content_matcher;
+        self
+    }
+
+    /// Append a response message to the (possibly empty) sequence of expected response
+    /// messages. Can be called multiple times to build up a sequence of responses.
+    ///
+    /// ```
+    /// use pact_consumer::builders::SyncMessageInteractionBuilder;
+    ///
+    /// let mut builder = SyncMessageInteractionBuilder::new("a request for data");
+    /// builder
+    ///   .response(|response| {
+    ///     response.contents = "first response".into();
+    ///   })
+    ///   .response(|response| {
+    ///     response.contents = "second response".into();
+    ///   });
+    /// ```
+    pub fn response<F>(&mut self, with_response: F) -> &mut Self
+        where F: FnOnce(&mut MessageContents)
+    {
+        let mut contents = MessageContents::default();
+        with_response(&mut contents);
+        self.messages.response.push(contents);
+        self
+    }
+
+    /// Build the `SynchronousMessages` interaction: any metadata generators configured on
+    /// the request or responses are applied to produce the final metadata, and the
+    /// interaction's key is computed from its (now-generated) contents.
+    pub fn build(&self) -> SynchronousMessages {
+        let mut messages = self.messages.clone();
+        messages.request.metadata = messages.request.generate_metadata();
+        for response in &mut messages.response {
+            response.metadata = response.generate_metadata();
+        }
+        messages.with_key()
+    }
+}
+
+impl Default for SyncMessageInteractionBuilder {
+    fn default() -> Self {
+        SyncMessageInteractionBuilder { messages: SynchronousMessages::default() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_a_request_and_a_sequence_of_responses() {
+        let mut builder = SyncMessageInteractionBuilder::new("a request for data");
+        let messages = builder
+            .given("data exists")
+            .request_body("the request")
+            .response(|response| { response.contents = "first response".into(); })
+            .response(|response| { response.contents = "second response".into(); })
+            .build();
+
+        assert_eq!(messages.description, "a request for data");
+        assert_eq!(messages.provider_states.len(), 1);
+        assert_eq!(messages.request.contents, OptionalBody::from("the request"));
+        assert_eq!(messages.response.len(), 2);
+        assert_eq!(messages.response[0].contents, OptionalBody::from("first response"));
+        assert_eq!(messages.response[1].contents, OptionalBody::from("second response"));
+        assert!(messages.key.is_some());
+    }
+
+    #[test]
+    fn request_content_matcher_defaults_to_default() {
+        let messages = SyncMessageInteractionBuilder::new("a request").build();
+        assert_eq!(messages.request.content_matcher, ContentMatcher::Default);
+    }
+
+    #[test]
+    fn request_content_matcher_can_be_set_to_json_rpc() {
+        let mut builder = SyncMessageInteractionBuilder::new("a json-rpc request");
+        let messages = builder.request_content_matcher(ContentMatcher::JsonRpc2).build();
+        assert_eq!(messages.request.content_matcher, ContentMatcher::JsonRpc2);
+    }
+
+    #[test]
+    fn build_applies_configured_metadata_generators() {
+        use pact_matching::models::generators::{Generator, GeneratorCategory};
+
+        let mut builder = SyncMessageInteractionBuilder::new("a request with generated metadata");
+        builder
+            .request_metadata("id", Value::from("to-be-replaced"))
+            .request_generators()
+            .add_generator_with_subcategory(&GeneratorCategory::METADATA, "id", Generator::Uuid(None));
+        builder.response(|response| {
+            response.metadata.insert("id".to_string(), Value::from("to-be-replaced"));
+            response.generators.add_generator_with_subcategory(&GeneratorCategory::METADATA, "id", Generator::Uuid(None));
+        });
+
+        let messages = builder.build();
+
+        assert_ne!(messages.request.metadata.get("id").unwrap(), &Value::from("to-be-replaced"));
+        assert_ne!(messages.response[0].metadata.get("id").unwrap(), &Value::from("to-be-replaced"));
+    }
+}
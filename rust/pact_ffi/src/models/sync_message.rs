@@ -0,0 +1,385 @@
+//! FFI support for building `SynchronousMessages` (request/response message) interactions.
+//!
+//! This mirrors the handle-based API already exposed for HTTP interactions
+//! (`pactffi_new_interaction`, `pactffi_with_body`, ...), but targets the V4
+//! `SynchronousMessages` interaction type, so that non-Rust consumers can author
+//! synchronous-message pacts. These handles live in a registry of this module's own rather
+//! than being attached to a `PactHandle`; callers get the built interaction out via
+//! `pactffi_sync_message_to_json` for embedding into a pact document themselves.
+
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::sync::{Mutex, OnceLock};
+
+use serde_json::{json, Value};
+
+use pact_matching::models::{generators, matchingrules};
+use pact_matching::models::v4::V4Interaction;
+use pact_matching::models::v4::message_parts::{ContentMatcher, MessageContents};
+use pact_matching::models::v4::sync_message::SynchronousMessages;
+use pact_models::PactSpecification;
+
+/// Handle to a `SynchronousMessages` interaction under construction. Returned by
+/// `pactffi_new_sync_message_interaction` and passed to the other functions in this module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct SyncMessageHandle(u32);
+
+/// Which part of the interaction a matching-rule/generator/metadata/content-matcher call
+/// should apply to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub enum InteractionPart {
+  /// The single request message
+  Request,
+  /// The most recently appended response message
+  Response
+}
+
+fn interactions() -> &'static Mutex<HashMap<u32, SynchronousMessages>> {
+  static INTERACTIONS: OnceLock<Mutex<HashMap<u32, SynchronousMessages>>> = OnceLock::new();
+  INTERACTIONS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn next_handle_id() -> u32 {
+  static NEXT_ID: OnceLock<Mutex<u32>> = OnceLock::new();
+  let next_id = NEXT_ID.get_or_init(|| Mutex::new(0));
+  let mut next_id = next_id.lock().unwrap();
+  *next_id += 1;
+  *next_id
+}
+
+fn with_interaction<F, R>(handle: SyncMessageHandle, f: F) -> Option<R>
+  where F: FnOnce(&mut SynchronousMessages) -> R
+{
+  let mut interactions = interactions().lock().unwrap();
+  interactions.get_mut(&handle.0).map(f)
+}
+
+/// Get mutable access to the request (`InteractionPart::Request`) or to the most recently
+/// appended response (`InteractionPart::Response`), if there is one.
+fn with_message_part<F, R>(interaction: &mut SynchronousMessages, part: InteractionPart, f: F) -> Option<R>
+  where F: FnOnce(&mut MessageContents) -> R
+{
+  match part {
+    InteractionPart::Request => Some(f(&mut interaction.request)),
+    InteractionPart::Response => interaction.response.last_mut().map(f)
+  }
+}
+
+/// Shallow-merges `incoming` into `existing`: top-level keys (matching-rule/generator
+/// categories, e.g. `"body"` or `"metadata"`) present in `incoming` replace the
+/// corresponding key in `existing`, and any key only present in `existing` is left alone.
+/// This is enough to let separate calls targeting different categories (e.g. one call for
+/// `body` rules, another for `metadata` rules) compose, without one wiping out the other.
+fn merge_json_objects(existing: Value, incoming: Value) -> Value {
+  match (existing, incoming) {
+    (Value::Object(mut existing_map), Value::Object(incoming_map)) => {
+      for (key, value) in incoming_map {
+        existing_map.insert(key, value);
+      }
+      Value::Object(existing_map)
+    }
+    (_, incoming) => incoming
+  }
+}
+
+unsafe fn optional_str(s: *const c_char) -> Option<String> {
+  if s.is_null() {
+    None
+  } else {
+    CStr::from_ptr(s).to_str().ok().map(|s| s.to_string())
+  }
+}
+
+/// Create a new `SynchronousMessages` interaction with the given description, returning a
+/// handle that can be passed to the other `pactffi_sync_message_*` functions.
+#[no_mangle]
+pub extern "C" fn pactffi_new_sync_message_interaction(description: *const c_char) -> SyncMessageHandle {
+  let description = unsafe { optional_str(description) }.unwrap_or_default();
+  let id = next_handle_id();
+  interactions().lock().unwrap().insert(id, SynchronousMessages {
+    description,
+    ..SynchronousMessages::default()
+  });
+  SyncMessageHandle(id)
+}
+
+/// Release a `SynchronousMessages` handle and any resources associated with it. The handle
+/// must not be used again after this call.
+#[no_mangle]
+pub extern "C" fn pactffi_sync_message_delete(handle: SyncMessageHandle) -> bool {
+  interactions().lock().unwrap().remove(&handle.0).is_some()
+}
+
+/// Set the contents of the request message.
+#[no_mangle]
+pub extern "C" fn pactffi_sync_message_request_contents(
+  handle: SyncMessageHandle,
+  contents: *const c_char
+) -> bool {
+  with_interaction(handle, |interaction| {
+    if let Some(contents) = unsafe { optional_str(contents) } {
+      interaction.request.contents = contents.into();
+      true
+    } else {
+      false
+    }
+  }).unwrap_or(false)
+}
+
+/// Append a new response message to the interaction's response sequence, and set its
+/// contents. Can be called multiple times to build up a sequence of expected responses.
+#[no_mangle]
+pub extern "C" fn pactffi_sync_message_add_response_contents(
+  handle: SyncMessageHandle,
+  contents: *const c_char
+) -> bool {
+  with_interaction(handle, |interaction| {
+    if let Some(contents) = unsafe { optional_str(contents) } {
+      let mut message = MessageContents::default();
+      message.contents = contents.into();
+      interaction.response.push(message);
+      true
+    } else {
+      false
+    }
+  }).unwrap_or(false)
+}
+
+/// Set a metadata value on the request, or on the most recently appended response,
+/// depending on `part`. `value` is parsed as JSON if possible, otherwise stored as a string.
+/// Returns `false` (and sets nothing) if `part` is `Response` but no response has been
+/// appended yet.
+#[no_mangle]
+pub extern "C" fn pactffi_sync_message_with_metadata(
+  handle: SyncMessageHandle,
+  part: InteractionPart,
+  key: *const c_char,
+  value: *const c_char
+) -> bool {
+  with_interaction(handle, |interaction| {
+    match (unsafe { optional_str(key) }, unsafe { optional_str(value) }) {
+      (Some(key), Some(value)) => {
+        let value = serde_json::from_str(&value).unwrap_or_else(|_| Value::String(value));
+        with_message_part(interaction, part, |message| { message.metadata.insert(key, value); })
+          .is_some()
+      }
+      _ => false
+    }
+  }).unwrap_or(false)
+}
+
+/// Set the content matcher for the request, or for the most recently appended response,
+/// depending on `part`. Pass `"jsonRpc2"` to treat the body as a JSON-RPC 2.0 envelope (or
+/// batch of envelopes) rather than matching it byte-for-byte; any other value (or `null`)
+/// resets it to the default. Returns `false` if `part` is `Response` but no response has
+/// been appended yet.
+#[no_mangle]
+pub extern "C" fn pactffi_sync_message_with_content_matcher(
+  handle: SyncMessageHandle,
+  part: InteractionPart,
+  content_matcher: *const c_char
+) -> bool {
+  with_interaction(handle, |interaction| {
+    let content_matcher = match unsafe { optional_str(content_matcher) } {
+      Some(ref matcher) if matcher == "jsonRpc2" => ContentMatcher::JsonRpc2,
+      _ => ContentMatcher::Default
+    };
+    with_message_part(interaction, part, |message| { message.content_matcher = content_matcher; })
+      .is_some()
+  }).unwrap_or(false)
+}
+
+/// Merge a `matchingRules` JSON fragment (as found in a pact file, e.g.
+/// `{"body": {"$.id": {"matchers": [{"match": "type"}]}}}`) into the request's or the most
+/// recently appended response's matching rules, depending on `part`. The merge is per
+/// category (the fragment's top-level keys, e.g. `"body"` or `"metadata"`): a category
+/// present in the fragment replaces that category's existing rules, and any other category
+/// already set on the message is left untouched - so a call adding `body` rules and a
+/// separate call adding `metadata` rules compose instead of one overwriting the other.
+/// Returns `false` if the JSON is malformed, or if `part` is `Response` but no response has
+/// been appended yet.
+#[no_mangle]
+pub extern "C" fn pactffi_sync_message_with_matching_rules(
+  handle: SyncMessageHandle,
+  part: InteractionPart,
+  matching_rules_json: *const c_char
+) -> bool {
+  with_interaction(handle, |interaction| {
+    let fragment = match unsafe { optional_str(matching_rules_json) } {
+      Some(json_str) => match serde_json::from_str::<Value>(&json_str) {
+        Ok(json) => json,
+        Err(_) => return false
+      },
+      None => return false
+    };
+    with_message_part(interaction, part, |message| {
+      let existing = matchingrules::matchers_to_json(&message.matching_rules, &PactSpecification::V4);
+      let merged = merge_json_objects(existing, fragment);
+      message.matching_rules = matchingrules::matchers_from_json(&json!({ "matchingRules": merged }), &None);
+    }).is_some()
+  }).unwrap_or(false)
+}
+
+/// Merge a `generators` JSON fragment (as found in a pact file, e.g.
+/// `{"metadata": {"correlation-id": {"type": "Uuid"}}}`) into the request's or the most
+/// recently appended response's generators, depending on `part`. As with
+/// `pactffi_sync_message_with_matching_rules`, the merge is per category, so a call adding
+/// `body` generators and a separate call adding `metadata` generators compose. Returns
+/// `false` if the JSON is malformed, or if `part` is `Response` but no response has been
+/// appended yet.
+#[no_mangle]
+pub extern "C" fn pactffi_sync_message_with_generators(
+  handle: SyncMessageHandle,
+  part: InteractionPart,
+  generators_json: *const c_char
+) -> bool {
+  with_interaction(handle, |interaction| {
+    let fragment = match unsafe { optional_str(generators_json) } {
+      Some(json_str) => match serde_json::from_str::<Value>(&json_str) {
+        Ok(json) => json,
+        Err(_) => return false
+      },
+      None => return false
+    };
+    with_message_part(interaction, part, |message| {
+      let existing = generators::generators_to_json(&message.generators, &PactSpecification::V4);
+      let merged = merge_json_objects(existing, fragment);
+      message.generators = generators::generators_from_json(&json!({ "generators": merged }));
+    }).is_some()
+  }).unwrap_or(false)
+}
+
+/// Verify a sequence of actual provider response bodies (a JSON array of response bodies,
+/// one per expected response) against the interaction's expected response sequence. Returns
+/// a JSON array of mismatch descriptions (empty if everything matched) as an owned string
+/// that must be freed with `pactffi_string_delete`, or `null` if the handle is invalid.
+#[no_mangle]
+pub extern "C" fn pactffi_sync_message_verify_responses(
+  handle: SyncMessageHandle,
+  actual_responses_json: *const c_char
+) -> *const c_char {
+  with_interaction(handle, |interaction| {
+    let actual_bodies: Vec<Value> = unsafe { optional_str(actual_responses_json) }
+      .and_then(|json_str| serde_json::from_str(&json_str).ok())
+      .unwrap_or_default();
+    let actual: Vec<MessageContents> = actual_bodies.into_iter()
+      .map(|body| MessageContents { contents: body.to_string().into(), ..MessageContents::default() })
+      .collect();
+
+    let mismatches = interaction.verify_response_sequence(&actual);
+    let json = serde_json::to_string(&mismatches).unwrap_or_default();
+    CString::new(json).unwrap_or_default().into_raw() as *const c_char
+  }).unwrap_or(std::ptr::null())
+}
+
+/// Serialise the interaction to its V4 pact JSON representation. The returned string is
+/// owned by the caller and must be freed with `pactffi_string_delete`.
+#[no_mangle]
+pub extern "C" fn pactffi_sync_message_to_json(handle: SyncMessageHandle) -> *const c_char {
+  with_interaction(handle, |interaction| {
+    let json = interaction.to_json().to_string();
+    CString::new(json).unwrap_or_default().into_raw() as *const c_char
+  }).unwrap_or(std::ptr::null())
+}
+
+#[cfg(test)]
+mod tests {
+  use std::ffi::CString;
+
+  use super::*;
+
+  fn to_cstring(s: &str) -> CString {
+    CString::new(s).unwrap()
+  }
+
+  #[test]
+  fn builds_a_request_and_a_sequence_of_responses() {
+    let description = to_cstring("a request for data");
+    let handle = pactffi_new_sync_message_interaction(description.as_ptr());
+
+    let request_body = to_cstring("the request");
+    assert!(pactffi_sync_message_request_contents(handle, request_body.as_ptr()));
+
+    let first = to_cstring("first response");
+    let second = to_cstring("second response");
+    assert!(pactffi_sync_message_add_response_contents(handle, first.as_ptr()));
+    assert!(pactffi_sync_message_add_response_contents(handle, second.as_ptr()));
+
+    let key = to_cstring("correlation-id");
+    let value = to_cstring("\"abc-123\"");
+    assert!(pactffi_sync_message_with_metadata(handle, InteractionPart::Response, key.as_ptr(), value.as_ptr()));
+
+    let json = pactffi_sync_message_to_json(handle);
+    assert!(!json.is_null());
+    let json_str = unsafe { CStr::from_ptr(json) }.to_str().unwrap().to_string();
+    assert!(json_str.contains("first response"));
+    assert!(json_str.contains("second response"));
+    assert!(json_str.contains("correlation-id"));
+
+    assert!(pactffi_sync_message_delete(handle));
+  }
+
+  #[test]
+  fn with_metadata_fails_for_an_unappended_response() {
+    let description = to_cstring("a request");
+    let handle = pactffi_new_sync_message_interaction(description.as_ptr());
+
+    let key = to_cstring("correlation-id");
+    let value = to_cstring("\"abc-123\"");
+    assert!(!pactffi_sync_message_with_metadata(handle, InteractionPart::Response, key.as_ptr(), value.as_ptr()));
+  }
+
+  #[test]
+  fn with_content_matcher_sets_json_rpc_mode_on_the_request() {
+    let description = to_cstring("a json-rpc request");
+    let handle = pactffi_new_sync_message_interaction(description.as_ptr());
+
+    let matcher = to_cstring("jsonRpc2");
+    assert!(pactffi_sync_message_with_content_matcher(handle, InteractionPart::Request, matcher.as_ptr()));
+
+    let json = pactffi_sync_message_to_json(handle);
+    let json_str = unsafe { CStr::from_ptr(json) }.to_str().unwrap().to_string();
+    assert!(json_str.contains("jsonRpc2"));
+  }
+
+  #[test]
+  fn verify_responses_reports_a_payload_mismatch() {
+    let description = to_cstring("an add request");
+    let handle = pactffi_new_sync_message_interaction(description.as_ptr());
+
+    let request_body = to_cstring(r#"{"jsonrpc":"2.0","method":"add","id":1}"#);
+    assert!(pactffi_sync_message_request_contents(handle, request_body.as_ptr()));
+    let matcher = to_cstring("jsonRpc2");
+    assert!(pactffi_sync_message_with_content_matcher(handle, InteractionPart::Request, matcher.as_ptr()));
+
+    let response_body = to_cstring(r#"{"jsonrpc":"2.0","result":3,"id":1}"#);
+    assert!(pactffi_sync_message_add_response_contents(handle, response_body.as_ptr()));
+    assert!(pactffi_sync_message_with_content_matcher(handle, InteractionPart::Response, matcher.as_ptr()));
+
+    let actual = to_cstring(r#"[{"jsonrpc":"2.0","result":4,"id":1}]"#);
+    let result = pactffi_sync_message_verify_responses(handle, actual.as_ptr());
+    let result_str = unsafe { CStr::from_ptr(result) }.to_str().unwrap().to_string();
+    assert!(result_str.contains("payload did not match"));
+  }
+
+  #[test]
+  fn with_matching_rules_merges_separate_calls_for_different_categories() {
+    let description = to_cstring("a request");
+    let handle = pactffi_new_sync_message_interaction(description.as_ptr());
+    assert!(pactffi_sync_message_add_response_contents(handle, to_cstring("the response").as_ptr()));
+
+    let body_rules = to_cstring(r#"{"body": {"$.id": {"matchers": [{"match": "type"}]}}}"#);
+    assert!(pactffi_sync_message_with_matching_rules(handle, InteractionPart::Response, body_rules.as_ptr()));
+
+    let metadata_rules = to_cstring(r#"{"metadata": {"correlation-id": {"matchers": [{"match": "regex", "regex": ".*"}]}}}"#);
+    assert!(pactffi_sync_message_with_matching_rules(handle, InteractionPart::Response, metadata_rules.as_ptr()));
+
+    let json = pactffi_sync_message_to_json(handle);
+    let json_str = unsafe { CStr::from_ptr(json) }.to_str().unwrap().to_string();
+    assert!(json_str.contains("\"body\""), "expected body rules from the first call to survive: {}", json_str);
+    assert!(json_str.contains("\"metadata\""), "expected metadata rules from the second call: {}", json_str);
+  }
+}
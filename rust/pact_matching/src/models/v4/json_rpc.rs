@@ -0,0 +1,229 @@
+//! Support for treating a message body as a JSON-RPC 2.0 envelope (or batch of envelopes),
+//! so that request/response messages can be correlated and matched on their RPC semantics
+//! rather than byte-for-byte.
+
+use anyhow::anyhow;
+use itertools::Itertools;
+use serde_json::Value;
+
+/// The JSON-RPC version required on every envelope.
+pub const JSONRPC_VERSION: &str = "2.0";
+
+/// A single parsed JSON-RPC 2.0 envelope, either a request (or notification) or a response.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonRpcEnvelope {
+  /// A request envelope. `id` is `None` for a notification.
+  Request {
+    /// Correlation ID, absent for notifications
+    id: Option<Value>,
+    /// The RPC method name
+    method: String,
+    /// The request parameters, if any
+    params: Option<Value>
+  },
+  /// A response envelope, with exactly one of `result` or `error` set.
+  Response {
+    /// Correlation ID, matching the request's `id`
+    id: Option<Value>,
+    /// The result payload, if the call succeeded
+    result: Option<Value>,
+    /// The error object, if the call failed
+    error: Option<Value>
+  }
+}
+
+impl JsonRpcEnvelope {
+  /// Parse and validate a single JSON-RPC request envelope.
+  pub fn parse_request(json: &Value) -> anyhow::Result<JsonRpcEnvelope> {
+    let object = json.as_object()
+      .ok_or_else(|| anyhow!("JSON-RPC request envelope must be a JSON object, got '{}'", json))?;
+    validate_version(object)?;
+    let method = object.get("method")
+      .and_then(|value| value.as_str())
+      .ok_or_else(|| anyhow!("JSON-RPC request envelope must have a string 'method', got '{}'", json))?;
+    if let Some(params) = object.get("params") {
+      if !params.is_object() && !params.is_array() {
+        return Err(anyhow!("JSON-RPC 'params' must be an object or array, got '{}'", params));
+      }
+    }
+    Ok(JsonRpcEnvelope::Request {
+      id: object.get("id").cloned(),
+      method: method.to_string(),
+      params: object.get("params").cloned()
+    })
+  }
+
+  /// Parse and validate a single JSON-RPC response envelope.
+  pub fn parse_response(json: &Value) -> anyhow::Result<JsonRpcEnvelope> {
+    let object = json.as_object()
+      .ok_or_else(|| anyhow!("JSON-RPC response envelope must be a JSON object, got '{}'", json))?;
+    validate_version(object)?;
+    let result = object.get("result").cloned();
+    let error = object.get("error").cloned();
+    match (&result, &error) {
+      (Some(_), Some(_)) =>
+        Err(anyhow!("JSON-RPC response envelope must not have both 'result' and 'error', got '{}'", json)),
+      (None, None) =>
+        Err(anyhow!("JSON-RPC response envelope must have exactly one of 'result' or 'error', got '{}'", json)),
+      _ => {
+        if let Some(error) = &error {
+          validate_error(error)?;
+        }
+        Ok(JsonRpcEnvelope::Response { id: object.get("id").cloned(), result, error })
+      }
+    }
+  }
+
+  /// The correlation ID for this envelope, absent for notifications.
+  pub fn id(&self) -> Option<&Value> {
+    match self {
+      JsonRpcEnvelope::Request { id, .. } => id.as_ref(),
+      JsonRpcEnvelope::Response { id, .. } => id.as_ref()
+    }
+  }
+
+  /// The inner payload that matching rules/generators should be applied to (the request's
+  /// `params`, the response's `result`, or the response error's `data`), rather than the
+  /// envelope plumbing around it.
+  pub fn payload(&self) -> Option<&Value> {
+    match self {
+      JsonRpcEnvelope::Request { params, .. } => params.as_ref(),
+      JsonRpcEnvelope::Response { result: Some(result), .. } => Some(result),
+      JsonRpcEnvelope::Response { error: Some(error), .. } => error.get("data"),
+      JsonRpcEnvelope::Response { .. } => None
+    }
+  }
+}
+
+fn validate_version(object: &serde_json::Map<String, Value>) -> anyhow::Result<()> {
+  match object.get("jsonrpc") {
+    Some(Value::String(version)) if version == JSONRPC_VERSION => Ok(()),
+    Some(version) => Err(anyhow!("JSON-RPC envelope must have 'jsonrpc' set to \"2.0\", got '{}'", version)),
+    None => Err(anyhow!("JSON-RPC envelope is missing the required 'jsonrpc' field"))
+  }
+}
+
+fn validate_error(error: &Value) -> anyhow::Result<()> {
+  let object = error.as_object()
+    .ok_or_else(|| anyhow!("JSON-RPC 'error' must be an object, got '{}'", error))?;
+  if !matches!(object.get("code"), Some(Value::Number(code)) if code.is_i64() || code.is_u64()) {
+    return Err(anyhow!("JSON-RPC 'error' must have an integer 'code', got '{}'", error));
+  }
+  if !matches!(object.get("message"), Some(Value::String(_))) {
+    return Err(anyhow!("JSON-RPC 'error' must have a string 'message', got '{}'", error));
+  }
+  Ok(())
+}
+
+/// A JSON-RPC body is either a single envelope, or a batch array of envelopes.
+fn envelopes(json: &Value) -> Vec<Value> {
+  match json {
+    Value::Array(values) => values.clone(),
+    _ => vec![json.clone()]
+  }
+}
+
+/// Parse the request side of a JSON-RPC body (a single envelope, or a batch array).
+pub fn parse_request_envelopes(json: &Value) -> anyhow::Result<Vec<JsonRpcEnvelope>> {
+  envelopes(json).iter().map(JsonRpcEnvelope::parse_request).collect()
+}
+
+/// Parse the response side of a JSON-RPC body (a single envelope, or a batch array).
+pub fn parse_response_envelopes(json: &Value) -> anyhow::Result<Vec<JsonRpcEnvelope>> {
+  envelopes(json).iter().map(JsonRpcEnvelope::parse_response).collect()
+}
+
+/// Given the request envelopes for a call and the response envelopes produced for it,
+/// return the `id`s of any responses that don't correlate to one of the requests. Batches
+/// are matched by `id`, not by position.
+pub fn uncorrelated_response_ids(
+  requests: &[JsonRpcEnvelope],
+  responses: &[JsonRpcEnvelope]
+) -> Vec<Option<Value>> {
+  let request_ids = requests.iter().map(|request| request.id().cloned()).collect_vec();
+  responses.iter()
+    .filter(|response| !request_ids.contains(&response.id().cloned()))
+    .map(|response| response.id().cloned())
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  #[test]
+  fn parses_a_well_formed_request_envelope() {
+    let envelope = JsonRpcEnvelope::parse_request(&json!({
+      "jsonrpc": "2.0", "method": "add", "params": [1, 2], "id": 1
+    })).unwrap();
+    match envelope {
+      JsonRpcEnvelope::Request { id, method, params } => {
+        assert_eq!(id, Some(json!(1)));
+        assert_eq!(method, "add");
+        assert_eq!(params, Some(json!([1, 2])));
+      }
+      _ => panic!("Expected a request envelope")
+    }
+  }
+
+  #[test]
+  fn rejects_a_request_envelope_with_the_wrong_jsonrpc_version() {
+    let result = JsonRpcEnvelope::parse_request(&json!({ "jsonrpc": "1.0", "method": "add" }));
+    assert!(result.is_err());
+  }
+
+  #[test]
+  fn rejects_a_request_envelope_missing_method() {
+    let result = JsonRpcEnvelope::parse_request(&json!({ "jsonrpc": "2.0" }));
+    assert!(result.is_err());
+  }
+
+  #[test]
+  fn parses_a_well_formed_result_response_envelope() {
+    let envelope = JsonRpcEnvelope::parse_response(&json!({
+      "jsonrpc": "2.0", "result": 3, "id": 1
+    })).unwrap();
+    assert_eq!(envelope.payload(), Some(&json!(3)));
+  }
+
+  #[test]
+  fn rejects_a_response_envelope_with_both_result_and_error() {
+    let result = JsonRpcEnvelope::parse_response(&json!({
+      "jsonrpc": "2.0", "result": 3, "error": { "code": -1, "message": "oops" }, "id": 1
+    }));
+    assert!(result.is_err());
+  }
+
+  #[test]
+  fn rejects_a_response_envelope_with_neither_result_nor_error() {
+    let result = JsonRpcEnvelope::parse_response(&json!({ "jsonrpc": "2.0", "id": 1 }));
+    assert!(result.is_err());
+  }
+
+  #[test]
+  fn error_payload_is_the_error_data() {
+    let envelope = JsonRpcEnvelope::parse_response(&json!({
+      "jsonrpc": "2.0",
+      "error": { "code": -1, "message": "oops", "data": { "detail": "bad id" } },
+      "id": 1
+    })).unwrap();
+    assert_eq!(envelope.payload(), Some(&json!({ "detail": "bad id" })));
+  }
+
+  #[test]
+  fn flags_responses_that_do_not_correlate_to_any_request() {
+    let requests = parse_request_envelopes(&json!([
+      { "jsonrpc": "2.0", "method": "add", "id": 1 },
+      { "jsonrpc": "2.0", "method": "sub", "id": 2 }
+    ])).unwrap();
+    let responses = parse_response_envelopes(&json!([
+      { "jsonrpc": "2.0", "result": 3, "id": 1 },
+      { "jsonrpc": "2.0", "result": 99, "id": 3 }
+    ])).unwrap();
+
+    let uncorrelated = uncorrelated_response_ids(&requests, &responses);
+    assert_eq!(uncorrelated, vec![Some(json!(3))]);
+  }
+}
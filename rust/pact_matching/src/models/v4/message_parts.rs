@@ -10,15 +10,36 @@ use pact_models::bodies::OptionalBody;
 use pact_models::PactSpecification;
 
 use crate::models::{generators, matchingrules};
-use crate::models::generators::generators_to_json;
+use crate::models::generators::{generators_to_json, GenerateValue};
 use crate::models::matchingrules::matchers_to_json;
 use crate::models::v4::http_parts::body_from_json;
+use crate::models::v4::json_rpc::{self, JsonRpcEnvelope};
 use crate::models::v4::{metadata_to_headers, calc_content_type};
 use pact_models::content_types::ContentType;
 use std::fmt::{Display, Formatter};
 use std::hash::{Hash, Hasher};
 use crate::models::json_utils::hash_json;
 
+/// The semantics to apply when matching a message's body, beyond plain content-type
+/// based matching.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum ContentMatcher {
+  /// Match the body using the configured matching rules, the default.
+  Default,
+  /// Treat the body as a JSON-RPC 2.0 envelope (or batch of envelopes): validate the
+  /// envelope invariants, correlate request/response `id`s, and, when verifying responses,
+  /// apply the `body` matching rules to each response's inner `result`/`error.data`
+  /// payload rather than the envelope plumbing. The request's `params` payload is not
+  /// matched, and generators are not applied to any payload.
+  JsonRpc2
+}
+
+impl Default for ContentMatcher {
+  fn default() -> Self {
+    ContentMatcher::Default
+  }
+}
+
 /// Contents of a message interaction
 #[derive(Default, Clone, Debug, Eq)]
 pub struct MessageContents {
@@ -30,6 +51,8 @@ pub struct MessageContents {
   pub matching_rules: matchingrules::MatchingRules,
   /// Generators
   pub generators: generators::Generators,
+  /// The content matcher to apply to this message's body
+  pub content_matcher: ContentMatcher,
 }
 
 impl MessageContents {
@@ -43,11 +66,16 @@ impl MessageContents {
         _ => hashmap! {}
       };
       let as_headers = metadata_to_headers(&metadata);
+      let content_matcher = match json.get("contentMatcher") {
+        Some(&Value::String(ref matcher)) if matcher == "jsonRpc2" => ContentMatcher::JsonRpc2,
+        _ => ContentMatcher::Default
+      };
       Ok(MessageContents {
         metadata,
         contents: body_from_json(&json, "contents", &as_headers),
         matching_rules: matchingrules::matchers_from_json(&json, &None),
-        generators: generators::generators_from_json(&json)
+        generators: generators::generators_from_json(&json),
+        content_matcher
       })
     } else {
       Err(anyhow!("Expected a JSON object for the message contents, got '{}'", json))
@@ -80,6 +108,11 @@ impl MessageContents {
       map.insert("generators".to_string(), generators_to_json(&self.generators, &PactSpecification::V4));
     }
 
+    if self.content_matcher == ContentMatcher::JsonRpc2 {
+      let map = json.as_object_mut().unwrap();
+      map.insert("contentMatcher".to_string(), Value::String("jsonRpc2".to_string()));
+    }
+
     json
   }
 
@@ -89,6 +122,71 @@ impl MessageContents {
   pub fn message_content_type(&self) -> Option<ContentType> {
     calc_content_type(&self.contents, &metadata_to_headers(&self.metadata))
   }
+
+  /// Returns the matching rules configured for the message metadata, scoped under the
+  /// `metadata` category so they don't collide with the body's matching rules.
+  pub fn metadata_matching_rules(&self) -> matchingrules::MatchingRuleCategory {
+    self.matching_rules.rules_for_category(matchingrules::Category::METADATA)
+  }
+
+  /// Applies any generators configured for the `metadata` category, returning a new set of
+  /// metadata with generated values substituted in. Keys without a configured generator are
+  /// left untouched.
+  pub fn generate_metadata(&self) -> HashMap<String, Value> {
+    let mut metadata = self.metadata.clone();
+    self.generators.apply_generator(&generators::GeneratorCategory::METADATA, &mut |key, generator| {
+      if let Some(value) = metadata.get(key).cloned() {
+        if let Ok(generated) = generator.generate_value(&value, &hashmap!{}) {
+          metadata.insert(key.to_string(), generated);
+        }
+      }
+    });
+    metadata
+  }
+
+  /// If this message is configured with the `JsonRpc2` content matcher, parse its body as
+  /// a JSON-RPC request envelope (or batch of envelopes).
+  pub fn as_json_rpc_request(&self) -> anyhow::Result<Vec<JsonRpcEnvelope>> {
+    self.require_json_rpc()?;
+    self.parse_as_json_rpc_request()
+  }
+
+  /// If this message is configured with the `JsonRpc2` content matcher, parse its body as
+  /// a JSON-RPC response envelope (or batch of envelopes).
+  pub fn as_json_rpc_response(&self) -> anyhow::Result<Vec<JsonRpcEnvelope>> {
+    self.require_json_rpc()?;
+    self.parse_as_json_rpc_response()
+  }
+
+  /// Parse this message's body as a JSON-RPC request envelope (or batch of envelopes),
+  /// regardless of whether the `JsonRpc2` content matcher is configured on `self`. Used to
+  /// parse an actual provider-produced message against an expected message's configured
+  /// mode, where the `JsonRpc2` flag only lives on the expected side.
+  pub(crate) fn parse_as_json_rpc_request(&self) -> anyhow::Result<Vec<JsonRpcEnvelope>> {
+    json_rpc::parse_request_envelopes(&self.json_rpc_body()?)
+  }
+
+  /// Parse this message's body as a JSON-RPC response envelope (or batch of envelopes),
+  /// regardless of whether the `JsonRpc2` content matcher is configured on `self`. See
+  /// [`Self::parse_as_json_rpc_request`].
+  pub(crate) fn parse_as_json_rpc_response(&self) -> anyhow::Result<Vec<JsonRpcEnvelope>> {
+    json_rpc::parse_response_envelopes(&self.json_rpc_body()?)
+  }
+
+  fn require_json_rpc(&self) -> anyhow::Result<()> {
+    if self.content_matcher == ContentMatcher::JsonRpc2 {
+      Ok(())
+    } else {
+      Err(anyhow!("This message is not configured with the JSON-RPC 2.0 content matcher"))
+    }
+  }
+
+  fn json_rpc_body(&self) -> anyhow::Result<Value> {
+    let bytes = self.contents.value()
+      .ok_or_else(|| anyhow!("Message has no body to parse as JSON-RPC"))?;
+    serde_json::from_slice(&bytes)
+      .map_err(|err| anyhow!("Message body is not valid JSON: {}", err))
+  }
 }
 
 impl Display for MessageContents {
@@ -107,12 +205,68 @@ impl Hash for MessageContents {
     }
     self.matching_rules.hash(state);
     self.generators.hash(state);
+    self.content_matcher.hash(state);
   }
 }
 
 impl PartialEq for MessageContents {
   fn eq(&self, other: &Self) -> bool {
     self.contents == other.contents && self.metadata == other.metadata &&
-      self.matching_rules == other.matching_rules && self.generators == other.generators
+      self.matching_rules == other.matching_rules && self.generators == other.generators &&
+      self.content_matcher == other.content_matcher
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use maplit::hashmap;
+  use serde_json::json;
+
+  use crate::models::generators::{Generator, GeneratorCategory};
+
+  use super::*;
+
+  #[test]
+  fn metadata_matching_rules_is_empty_by_default() {
+    let message = MessageContents::default();
+    assert!(message.metadata_matching_rules().is_empty());
+  }
+
+  #[test]
+  fn metadata_matching_rules_is_scoped_to_the_metadata_category() {
+    let message = MessageContents {
+      matching_rules: matchingrules::matchers_from_json(&json!({
+        "matchingRules": {
+          "metadata": {
+            "partition": { "matchers": [{ "match": "type" }] }
+          }
+        }
+      }), &None),
+      .. MessageContents::default()
+    };
+
+    assert!(!message.metadata_matching_rules().is_empty());
+  }
+
+  #[test]
+  fn generate_metadata_leaves_keys_without_a_generator_untouched() {
+    let message = MessageContents {
+      metadata: hashmap! { "partition".to_string() => json!(1) },
+      .. MessageContents::default()
+    };
+
+    assert_eq!(message.generate_metadata(), message.metadata);
+  }
+
+  #[test]
+  fn generate_metadata_applies_configured_generators() {
+    let mut message = MessageContents {
+      metadata: hashmap! { "id".to_string() => json!("to-be-replaced") },
+      .. MessageContents::default()
+    };
+    message.generators.add_generator_with_subcategory(&GeneratorCategory::METADATA, "id", Generator::Uuid(None));
+
+    let generated = message.generate_metadata();
+    assert_ne!(generated.get("id"), Some(&json!("to-be-replaced")));
   }
 }
@@ -17,10 +17,12 @@ use pact_models::provider_states::{self, ProviderState};
 
 use crate::models::{Interaction, RequestResponseInteraction};
 use crate::models::json_utils::json_to_string;
+use crate::models::matchingrules;
 use crate::models::matchingrules::MatchingRules;
 use crate::models::message::Message;
 use crate::models::v4::{AsynchronousMessage, SynchronousHttp, V4Interaction, V4InteractionType};
-use crate::models::v4::message_parts::MessageContents;
+use crate::models::v4::json_rpc;
+use crate::models::v4::message_parts::{ContentMatcher, MessageContents};
 
 /// Synchronous interactions as a request message to a sequence of response messages
 #[derive(Debug, Clone, Eq)]
@@ -60,6 +62,179 @@ impl SynchronousMessages {
     }
   }
 
+  /// If the request is configured with the `JsonRpc2` content matcher, validate that the
+  /// request and every response are well-formed JSON-RPC 2.0 envelopes, and that every
+  /// response correlates to the request by `id`.
+  pub fn validate_json_rpc(&self) -> anyhow::Result<()> {
+    if self.request.content_matcher != ContentMatcher::JsonRpc2 {
+      return Ok(());
+    }
+
+    let requests = self.request.as_json_rpc_request()?;
+    for response in &self.response {
+      let responses = response.as_json_rpc_response()?;
+      let uncorrelated = json_rpc::uncorrelated_response_ids(&requests, &responses);
+      if !uncorrelated.is_empty() {
+        return Err(anyhow!(
+          "JSON-RPC response(s) do not correlate to the request by id: {:?}", uncorrelated
+        ));
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Returns the full ordered sequence of expected response messages for verification, as
+  /// `(index, contents, metadata)` tuples. Unlike `contents_for_verification` (which only
+  /// ever returns the first expected response, for backwards compatibility), this exposes
+  /// every response in the `response` sequence so a verifier can check each one in turn.
+  pub fn responses_for_verification(&self) -> Vec<(usize, OptionalBody, HashMap<String, Value>)> {
+    self.response.iter().enumerate()
+      .map(|(index, message)| (index, message.contents.clone(), message.metadata.clone()))
+      .collect()
+  }
+
+  /// Compares a sequence of provider-produced response messages against the expected
+  /// response sequence, matching each by index. Returns one mismatch description per
+  /// problem found: a single entry if the provider returned a different number of
+  /// responses than expected, one entry for each expected response the provider didn't
+  /// produce, plus one entry for each index whose contents or metadata don't match. Extra
+  /// responses beyond the expected sequence are only reported through the count entry.
+  ///
+  /// If the request uses the `JsonRpc2` content matcher, this also validates that the
+  /// expected request/response pair is well-formed (via `validate_json_rpc`), and that
+  /// each actual response correlates to the request by `id`; in that mode, comparison is
+  /// done against each envelope's inner `payload()` (`params`/`result`/`error.data`)
+  /// rather than the raw envelope bytes, since the envelope plumbing itself is validated
+  /// separately. Matching rules configured in the expected response's `body` category are
+  /// applied to that payload (via `payload_matches`, with `$` being the payload itself), so a
+  /// type/regex matcher on e.g. `$.amount` for a `result` of `{"amount": ...}` is honoured
+  /// instead of requiring the payload to be byte-for-byte
+  /// identical - the same is true of the plain (non-JSON-RPC) response body, provided it
+  /// parses as JSON; a non-JSON body falls back to exact equality, since path-based
+  /// matching rules have no meaning outside of JSON.
+  pub fn verify_response_sequence(&self, actual: &[MessageContents]) -> Vec<String> {
+    let mut mismatches = vec![];
+
+    if let Err(err) = self.validate_json_rpc() {
+      mismatches.push(format!("Expected JSON-RPC request/response are not well-formed: {}", err));
+    }
+
+    if actual.len() != self.response.len() {
+      mismatches.push(format!(
+        "Expected {} response message(s) but the provider produced {}",
+        self.response.len(), actual.len()
+      ));
+    }
+
+    let json_rpc_requests = if self.request.content_matcher == ContentMatcher::JsonRpc2 {
+      self.request.parse_as_json_rpc_request().ok()
+    } else {
+      None
+    };
+
+    for (index, expected) in self.response.iter().enumerate() {
+      let actual = match actual.get(index) {
+        Some(actual) => actual,
+        None => {
+          mismatches.push(format!("Response message {} is missing from the provider's response", index));
+          continue;
+        }
+      };
+
+      if expected.content_matcher == ContentMatcher::JsonRpc2 {
+        match actual.parse_as_json_rpc_response() {
+          Ok(actual_envelopes) => {
+            if let Some(requests) = &json_rpc_requests {
+              let uncorrelated = json_rpc::uncorrelated_response_ids(requests, &actual_envelopes);
+              if !uncorrelated.is_empty() {
+                mismatches.push(format!(
+                  "Response message {} has response(s) that do not correlate to the request by id: {:?}",
+                  index, uncorrelated
+                ));
+              }
+            }
+
+            let expected_payloads = expected.parse_as_json_rpc_response()
+              .map(|envelopes| envelopes.iter().map(|envelope| envelope.payload().cloned()).collect_vec())
+              .unwrap_or_default();
+            let actual_payloads = actual_envelopes.iter()
+              .map(|envelope| envelope.payload().cloned())
+              .collect_vec();
+            let body_rules = expected.matching_rules.rules_for_category(matchingrules::Category::BODY);
+            let payloads_match = expected_payloads.len() == actual_payloads.len() &&
+              expected_payloads.iter().zip(actual_payloads.iter()).all(|(expected_payload, actual_payload)| {
+                match (expected_payload, actual_payload) {
+                  (Some(expected_payload), Some(actual_payload)) =>
+                    payload_matches(&body_rules, &["$".to_string()], expected_payload, actual_payload),
+                  (None, None) => true,
+                  _ => false
+                }
+              });
+            if !payloads_match {
+              mismatches.push(format!(
+                "Response message {} payload did not match: expected {:?}, got {:?}",
+                index, expected_payloads, actual_payloads
+              ));
+            }
+          }
+          Err(err) => mismatches.push(format!(
+            "Response message {} is not a valid JSON-RPC response envelope: {}", index, err
+          ))
+        }
+      } else {
+        match (serde_json::from_slice::<Value>(&expected.contents.value().unwrap_or_default()),
+               serde_json::from_slice::<Value>(&actual.contents.value().unwrap_or_default())) {
+          (Ok(expected_json), Ok(actual_json)) => {
+            let body_rules = expected.matching_rules.rules_for_category(matchingrules::Category::BODY);
+            if !payload_matches(&body_rules, &["$".to_string()], &expected_json, &actual_json) {
+              mismatches.push(format!(
+                "Response message {} did not match: expected {}, got {}",
+                index, expected.contents, actual.contents
+              ));
+            }
+          }
+          _ => if actual.contents != expected.contents {
+            mismatches.push(format!(
+              "Response message {} did not match: expected {}, got {}",
+              index, expected.contents, actual.contents
+            ));
+          }
+        }
+      }
+
+      mismatches.extend(Self::metadata_mismatches(index, expected, actual));
+    }
+
+    mismatches
+  }
+
+  /// Compares an expected response's metadata against the provider's actual metadata, one
+  /// key at a time. A key covered by a configured `metadata` matching rule defers to that
+  /// rule instead of being compared for exact equality; every other key still requires
+  /// exact equality, so a rule on one key (e.g. a regex on `correlation-id`) doesn't blind
+  /// this check to mismatches on unrelated keys. Any generators configured on a key are
+  /// applied (via `generate_metadata`) before the comparison, so a dynamically-generated
+  /// expected value isn't flagged as a mismatch.
+  fn metadata_mismatches(index: usize, expected: &MessageContents, actual: &MessageContents) -> Vec<String> {
+    let metadata_rules = expected.metadata_matching_rules();
+    let mut mismatches = vec![];
+    for (key, value) in expected.generate_metadata() {
+      match actual.metadata.get(&key) {
+        Some(actual_value) if !payload_matches(&metadata_rules, &[key.clone()], &value, actual_value) =>
+          mismatches.push(format!(
+            "Response message {} metadata '{}' did not match: expected {}, got {}",
+            index, key, value, actual_value
+          )),
+        None =>
+          mismatches.push(format!("Response message {} is missing expected metadata '{}'", index, key)),
+        _ => ()
+      }
+    }
+
+    mismatches
+  }
+
   /// Parse the JSON into a SynchronousMessages structure
   pub fn from_json(json: &Value, index: usize) -> anyhow::Result<SynchronousMessages> {
     if json.is_object() {
@@ -119,6 +294,54 @@ impl SynchronousMessages {
   }
 }
 
+/// Decides whether `actual` matches `expected` under a single matching `rule`. Only the
+/// rules meaningful for a scalar JSON value are implemented here - `Type` (same JSON type)
+/// and `Regex` (actual, stringified, matches the pattern) - anything else falls back to
+/// requiring the two values to be equal, same as if no rule were configured.
+fn value_matches_rule(rule: &matchingrules::MatchingRule, expected: &Value, actual: &Value) -> bool {
+  match rule {
+    matchingrules::MatchingRule::Type =>
+      std::mem::discriminant(expected) == std::mem::discriminant(actual),
+    matchingrules::MatchingRule::Regex(pattern) => {
+      let actual_str = actual.as_str().map(str::to_string).unwrap_or_else(|| actual.to_string());
+      regex::Regex::new(pattern).map(|re| re.is_match(&actual_str)).unwrap_or(false)
+    },
+    _ => expected == actual
+  }
+}
+
+/// Recursively compares `expected` against `actual`, consulting `rules` for each path
+/// visited. A path with a matching rule configured (resolved via
+/// `MatchingRuleCategory::select_best_matcher`) defers to that rule instead of requiring
+/// structural equality; a path without one falls back to equality, recursing into objects
+/// and arrays so that a rule nested deeper in the body (e.g. on `$.result.amount`) is still
+/// found and applied.
+fn payload_matches(rules: &matchingrules::MatchingRuleCategory, path: &[String], expected: &Value, actual: &Value) -> bool {
+  let path_refs = path.iter().map(String::as_str).collect_vec();
+  let matcher = rules.select_best_matcher(&path_refs);
+  if !matcher.is_empty() {
+    return matcher.rules.iter().all(|rule| value_matches_rule(rule, expected, actual));
+  }
+
+  match (expected, actual) {
+    (Value::Object(expected_map), Value::Object(_)) => expected_map.iter().all(|(key, value)| {
+      actual.get(key).map(|actual_value| {
+        let mut child_path = path.to_vec();
+        child_path.push(key.clone());
+        payload_matches(rules, &child_path, value, actual_value)
+      }).unwrap_or(false)
+    }),
+    (Value::Array(expected_items), Value::Array(actual_items)) =>
+      expected_items.len() == actual_items.len() &&
+        expected_items.iter().zip(actual_items.iter()).enumerate().all(|(index, (value, actual_value))| {
+          let mut child_path = path.to_vec();
+          child_path.push(index.to_string());
+          payload_matches(rules, &child_path, value, actual_value)
+        }),
+    _ => expected == actual
+  }
+}
+
 impl V4Interaction for SynchronousMessages {
   fn to_json(&self) -> Value {
     let mut json = json!({
@@ -212,7 +435,14 @@ impl Interaction for SynchronousMessages {
   }
 
   fn contents_for_verification(&self) -> OptionalBody {
-    self.response.first().map(|message| message.contents.clone()).unwrap_or_default()
+    // Only the first response is exposed here, for backwards compatibility with verifiers
+    // that expect a single body; it's derived from the same sequence that
+    // `responses_for_verification`/`verify_response_sequence` expose in full, so a verifier
+    // that upgrades to check the whole response sequence stays consistent with this.
+    self.responses_for_verification().into_iter()
+      .next()
+      .map(|(_, contents, _)| contents)
+      .unwrap_or_default()
   }
 
   fn content_type(&self) -> Option<ContentType> {
@@ -296,3 +526,256 @@ impl Display for SynchronousMessages {
            pending, self.id, self.description, self.provider_states, self.request, self.response)
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use maplit::hashmap;
+  use serde_json::json;
+
+  use crate::models::v4::message_parts::ContentMatcher;
+
+  use super::*;
+
+  fn json_rpc_message(body: Value) -> MessageContents {
+    MessageContents {
+      contents: OptionalBody::Present(body.to_string().into(), None, None),
+      content_matcher: ContentMatcher::JsonRpc2,
+      .. MessageContents::default()
+    }
+  }
+
+  #[test]
+  fn validate_json_rpc_passes_for_a_well_formed_correlated_pact() {
+    let messages = SynchronousMessages {
+      request: json_rpc_message(json!({ "jsonrpc": "2.0", "method": "add", "id": 1 })),
+      response: vec![json_rpc_message(json!({ "jsonrpc": "2.0", "result": 3, "id": 1 }))],
+      .. SynchronousMessages::default()
+    };
+
+    assert!(messages.validate_json_rpc().is_ok());
+  }
+
+  #[test]
+  fn validate_json_rpc_fails_when_the_response_id_does_not_correlate() {
+    let messages = SynchronousMessages {
+      request: json_rpc_message(json!({ "jsonrpc": "2.0", "method": "add", "id": 1 })),
+      response: vec![json_rpc_message(json!({ "jsonrpc": "2.0", "result": 3, "id": 2 }))],
+      .. SynchronousMessages::default()
+    };
+
+    assert!(messages.validate_json_rpc().is_err());
+  }
+
+  #[test]
+  fn verify_response_sequence_matches_on_payload_not_envelope_plumbing() {
+    let messages = SynchronousMessages {
+      request: json_rpc_message(json!({ "jsonrpc": "2.0", "method": "add", "id": 1 })),
+      response: vec![json_rpc_message(json!({ "jsonrpc": "2.0", "result": 3, "id": 1 }))],
+      .. SynchronousMessages::default()
+    };
+
+    // Same result payload, but a different id on the envelope - the payload comparison
+    // should still pass, because the id is plumbing, not the payload under test. (The
+    // differing id is reported separately, as a correlation mismatch.)
+    let actual = vec![json_rpc_message(json!({ "jsonrpc": "2.0", "result": 3, "id": 99 }))];
+
+    let mismatches = messages.verify_response_sequence(&actual);
+    assert!(
+      mismatches.iter().all(|mismatch| !mismatch.contains("payload did not match")),
+      "did not expect a payload mismatch, got {:?}", mismatches
+    );
+  }
+
+  #[test]
+  fn verify_response_sequence_flags_a_different_result_payload() {
+    let messages = SynchronousMessages {
+      request: json_rpc_message(json!({ "jsonrpc": "2.0", "method": "add", "id": 1 })),
+      response: vec![json_rpc_message(json!({ "jsonrpc": "2.0", "result": 3, "id": 1 }))],
+      .. SynchronousMessages::default()
+    };
+    let actual = vec![json_rpc_message(json!({ "jsonrpc": "2.0", "result": 4, "id": 1 }))];
+
+    let mismatches = messages.verify_response_sequence(&actual);
+    assert!(mismatches.iter().any(|mismatch| mismatch.contains("payload did not match")));
+  }
+
+  #[test]
+  fn verify_response_sequence_applies_body_matching_rules_to_json_rpc_payloads() {
+    let matching_rules = crate::models::matchingrules::matchers_from_json(&json!({
+      "matchingRules": {
+        "body": {
+          "$": { "matchers": [{ "match": "type" }] }
+        }
+      }
+    }), &None);
+    let messages = SynchronousMessages {
+      request: json_rpc_message(json!({ "jsonrpc": "2.0", "method": "add", "id": 1 })),
+      response: vec![MessageContents {
+        matching_rules,
+        .. json_rpc_message(json!({ "jsonrpc": "2.0", "result": 3, "id": 1 }))
+      }],
+      .. SynchronousMessages::default()
+    };
+    // Different result value, but the same type - a type matcher on the payload should
+    // treat this as a match rather than requiring the exact value 3.
+    let actual = vec![json_rpc_message(json!({ "jsonrpc": "2.0", "result": 999, "id": 1 }))];
+
+    let mismatches = messages.verify_response_sequence(&actual);
+    assert!(
+      mismatches.iter().all(|mismatch| !mismatch.contains("payload did not match")),
+      "did not expect a payload mismatch, got {:?}", mismatches
+    );
+  }
+
+  #[test]
+  fn verify_response_sequence_flags_uncorrelated_responses() {
+    let messages = SynchronousMessages {
+      request: json_rpc_message(json!({ "jsonrpc": "2.0", "method": "add", "id": 1 })),
+      response: vec![json_rpc_message(json!({ "jsonrpc": "2.0", "result": 3, "id": 1 }))],
+      .. SynchronousMessages::default()
+    };
+    let actual = vec![json_rpc_message(json!({ "jsonrpc": "2.0", "result": 3, "id": 2 }))];
+
+    let mismatches = messages.verify_response_sequence(&actual);
+    assert!(mismatches.iter().any(|mismatch| mismatch.contains("do not correlate")));
+  }
+
+  #[test]
+  fn contents_for_verification_is_derived_from_responses_for_verification() {
+    let messages = SynchronousMessages {
+      response: vec![
+        MessageContents { contents: "first".into(), .. MessageContents::default() },
+        MessageContents { contents: "second".into(), .. MessageContents::default() }
+      ],
+      .. SynchronousMessages::default()
+    };
+
+    let expected = messages.responses_for_verification().first().unwrap().1.clone();
+    assert_eq!(messages.contents_for_verification(), expected);
+    assert_eq!(messages.contents_for_verification(), OptionalBody::from("first"));
+  }
+
+  #[test]
+  fn verify_response_sequence_applies_body_matching_rules_to_plain_json_bodies() {
+    let matching_rules = crate::models::matchingrules::matchers_from_json(&json!({
+      "matchingRules": {
+        "body": {
+          "$.amount": { "matchers": [{ "match": "type" }] }
+        }
+      }
+    }), &None);
+    let messages = SynchronousMessages {
+      response: vec![MessageContents {
+        contents: OptionalBody::Present(json!({ "amount": 100 }).to_string().into(), None, None),
+        matching_rules,
+        .. MessageContents::default()
+      }],
+      .. SynchronousMessages::default()
+    };
+    // Different amount, but the same type - a type matcher on $.amount should treat this
+    // as a match rather than requiring the exact value 100.
+    let actual = vec![MessageContents {
+      contents: OptionalBody::Present(json!({ "amount": 250 }).to_string().into(), None, None),
+      .. MessageContents::default()
+    }];
+
+    let mismatches = messages.verify_response_sequence(&actual);
+    assert!(
+      mismatches.iter().all(|mismatch| !mismatch.contains("did not match")),
+      "did not expect a body mismatch, got {:?}", mismatches
+    );
+  }
+
+  #[test]
+  fn verify_response_sequence_flags_length_mismatches() {
+    let messages = SynchronousMessages {
+      response: vec![MessageContents::default(), MessageContents::default()],
+      .. SynchronousMessages::default()
+    };
+
+    let mismatches = messages.verify_response_sequence(&[MessageContents::default()]);
+    assert!(mismatches.iter().any(|mismatch| mismatch.contains("Expected 2 response message(s)")));
+  }
+
+  #[test]
+  fn verify_response_sequence_flags_a_missing_metadata_value() {
+    let messages = SynchronousMessages {
+      response: vec![MessageContents {
+        contents: "body".into(),
+        metadata: hashmap! { "partition".to_string() => json!(1) },
+        .. MessageContents::default()
+      }],
+      .. SynchronousMessages::default()
+    };
+    let actual = vec![MessageContents { contents: "body".into(), .. MessageContents::default() }];
+
+    let mismatches = messages.verify_response_sequence(&actual);
+    assert!(mismatches.iter().any(|mismatch| mismatch.contains("missing expected metadata 'partition'")));
+  }
+
+  #[test]
+  fn verify_response_sequence_ignores_metadata_keys_covered_by_a_matching_rule() {
+    let matching_rules = crate::models::matchingrules::matchers_from_json(&json!({
+      "matchingRules": {
+        "metadata": {
+          "partition": { "matchers": [{ "match": "type" }] }
+        }
+      }
+    }), &None);
+    let messages = SynchronousMessages {
+      response: vec![MessageContents {
+        contents: "body".into(),
+        metadata: hashmap! { "partition".to_string() => json!(1) },
+        matching_rules,
+        .. MessageContents::default()
+      }],
+      .. SynchronousMessages::default()
+    };
+    let actual = vec![MessageContents {
+      contents: "body".into(),
+      metadata: hashmap! { "partition".to_string() => json!(99) },
+      .. MessageContents::default()
+    }];
+
+    let mismatches = messages.verify_response_sequence(&actual);
+    assert!(mismatches.iter().all(|mismatch| !mismatch.contains("metadata")));
+  }
+
+  #[test]
+  fn verify_response_sequence_only_skips_the_metadata_key_covered_by_its_rule() {
+    let matching_rules = crate::models::matchingrules::matchers_from_json(&json!({
+      "matchingRules": {
+        "metadata": {
+          "correlation-id": { "matchers": [{ "match": "regex", "regex": ".*" }] }
+        }
+      }
+    }), &None);
+    let messages = SynchronousMessages {
+      response: vec![MessageContents {
+        contents: "body".into(),
+        metadata: hashmap! {
+          "correlation-id".to_string() => json!("abc-123"),
+          "retry-count".to_string() => json!(0)
+        },
+        matching_rules,
+        .. MessageContents::default()
+      }],
+      .. SynchronousMessages::default()
+    };
+    // correlation-id differs but is covered by a regex rule - should not be flagged.
+    // retry-count differs and has no rule of its own - should still be flagged, even
+    // though the message has a matching rule configured elsewhere in its metadata.
+    let actual = vec![MessageContents {
+      contents: "body".into(),
+      metadata: hashmap! {
+        "correlation-id".to_string() => json!("xyz-789"),
+        "retry-count".to_string() => json!(3)
+      },
+      .. MessageContents::default()
+    }];
+
+    let mismatches = messages.verify_response_sequence(&actual);
+    assert!(mismatches.iter().all(|mismatch| !mismatch.contains("'correlation-id'")));
+    assert!(mismatches.iter().any(|mismatch| mismatch.contains("'retry-count'")));
+  }
+}